@@ -2,29 +2,76 @@ use std::path::*;
 use std::process::*;
 use std::time::*;
 use std::fs::*;
-use std::io::{Read, Seek, SeekFrom};
+use std::collections::HashMap;
+
+/// Maps engine symbol names to their fixed runtime address, e.g. as loaded
+/// from a Dolphin `.map`/`symbols.txt` file. Used to resolve symbols that
+/// are referenced but not defined by any assembled code.
+type SymbolMap = HashMap<String, u32>;
 
 struct Args {
     pub asm_path: PathBuf,
     pub out_path: PathBuf,
     pub temp_path: PathBuf,
     pub as_path: PathBuf,
+    pub symbol_map: SymbolMap,
+    pub cache_dir: PathBuf,
+    /// Passed as an extra `-I` to every assembly job, so any file can
+    /// `.include` a shared macro/constant file by name regardless of its
+    /// own location in the tree. Always the top-level asm folder.
+    pub shared_include_dir: PathBuf,
+    /// `prelude.inc` at the root of the asm folder, if present. Auto-included
+    /// into every assembly job so register-alias macros and engine address
+    /// constants only need to be defined once.
+    pub prelude_path: Option<PathBuf>,
 }
 
+const PRELUDE_FILE_NAME: &'static str = "prelude.inc";
+
 const USAGE: &'static str = "USAGE:
-    hgecko <path/to/asm/folder> <path/to/output/codes.gct>
+    hgecko <path/to/asm/folder> <path/to/output/codes.gct> [path/to/symbols.map]
 ";
 
 const ERROR_STR: &'static str = "\x1B[31mERROR:\x1B[0m";
 const WARNING_STR: &'static str = "\x1B[33mWARNING:\x1B[0m";
 
+// PowerPC ELF relocation types (R_PPC_*) that the assembler can emit for
+// branches and loads against undefined (extern) symbols.
+const R_PPC_ADDR32: u32 = 1;
+const R_PPC_ADDR16_LO: u32 = 4;
+const R_PPC_ADDR16_HI: u32 = 5;
+const R_PPC_ADDR16_HA: u32 = 6;
+const R_PPC_ADDR14: u32 = 7;
+const R_PPC_REL24: u32 = 10;
+const R_PPC_REL14: u32 = 11;
+
+/// Which Gecko opcode a compiled target should be emitted as.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum CodeKind {
+    /// `C2`: insert a branch hook at `addr`, executed every time it's hit.
+    C2,
+    /// `C0`: execute-once assembly, run a single time when codes are applied.
+    C0,
+    /// `04`: write a single 32-bit word at `addr`.
+    Write32,
+    /// `06`: write an arbitrary byte blob (from the file's `.data` section) at `addr`.
+    Data,
+}
+
+/// One injection site declared by a file's header directives: where to patch,
+/// and which Gecko code type to emit it as.
+struct Target {
+    pub addr: u32,
+    pub kind: CodeKind,
+}
+
 fn parse_args() -> Args {
     let args = std::env::args().collect::<Vec<_>>();
-    if args.len() != 3 {
+    if args.len() != 3 && args.len() != 4 {
         print!("{}", USAGE);
         exit(1);
     }
-    
+
     let devkitppc = match std::env::var_os("DEVKITPPC") {
         Some(d) => d,
         None => {
@@ -35,30 +82,81 @@ and ensure the DEVKITPPC environment variable is set.");
         }
     };
     let as_path = Path::new(&devkitppc).join(Path::new("bin/powerpc-eabi-as"));
-    
-    let asm_path = Path::new(&args[1]).into();
-    let out_path = Path::new(&args[2]).into();
-    
+
+    let asm_path: PathBuf = Path::new(&args[1]).into();
+    let out_path: PathBuf = Path::new(&args[2]).into();
+    let symbol_map = match args.get(3) {
+        Some(p) => parse_symbol_map(Path::new(p)),
+        None => SymbolMap::new(),
+    };
+    let cache_dir = out_path.parent().unwrap_or(Path::new(".")).join("hgecko-cache");
+    let prelude_path = {
+        let p = asm_path.join(PRELUDE_FILE_NAME);
+        if p.try_exists().is_ok_and(|e| e) { Some(p) } else { None }
+    };
+    let shared_include_dir = asm_path.clone();
+
     let args = Args {
         asm_path,
         out_path,
         temp_path: std::env::temp_dir(),
         as_path,
+        symbol_map,
+        cache_dir,
+        shared_include_dir,
+        prelude_path,
     };
-    
+
     if !args.asm_path.try_exists().is_ok_and(|e| e) {
         eprintln!("{ERROR_STR} ASM path '{}' does not exist", args.asm_path.display());
         exit(1);
     }
-    
+
     if !args.as_path.try_exists().is_ok_and(|e| e) {
         eprintln!("{ERROR_STR} GNU assembler path '{}' does not exist!", args.as_path.display());
         exit(1);
     }
-    
+
     args
 }
 
+/// Parses a symbol map file of `name = 0x80xxxxxx` lines (blank lines and
+/// `#` comments are ignored) into a name -> address table used to resolve
+/// symbols left undefined by the assembled code.
+fn parse_symbol_map(path: &Path) -> SymbolMap {
+    let text = match read_to_string(path) {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("{ERROR_STR} Failed to read symbol map '{}': {}", path.display(), e);
+            exit(1);
+        }
+    };
+
+    let mut map = SymbolMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+
+        let Some((name, addr)) = line.split_once('=') else {
+            eprintln!("{ERROR_STR} Symbol map '{}' line {}: expected 'name = 0xADDRESS'", path.display(), i + 1);
+            exit(1);
+        };
+        let name = name.trim();
+        let addr_str = addr.trim().trim_start_matches("0x").trim_start_matches("0X");
+        let addr = match u32::from_str_radix(addr_str, 16) {
+            Ok(a) => a,
+            Err(_) => {
+                eprintln!("{ERROR_STR} Symbol map '{}' line {}: invalid address '{}'", path.display(), i + 1, addr.trim());
+                exit(1);
+            }
+        };
+
+        map.insert(name.to_string(), addr);
+    }
+
+    map
+}
+
 fn collect_asm(asm_paths: &mut Vec<PathBuf>, path: &Path) {
     let iter = match path.read_dir() {
         Ok(i) => i,
@@ -81,119 +179,189 @@ fn collect_asm(asm_paths: &mut Vec<PathBuf>, path: &Path) {
 }
 
 struct Code {
-    pub addr: u32,
-    pub code: Vec<u8>,
+    pub targets: Vec<Target>,
+    /// One relocated copy of the assembled `.text` per non-`Data` target, in
+    /// the same order as `targets` filtered to non-`Data` kinds. A separate
+    /// copy is needed per site because `R_PPC_REL24`/`ADDR32` relocations
+    /// bake in the placement address, and the same payload can be installed
+    /// at several different addresses.
+    pub code: Vec<Vec<u8>>,
+    /// Assembled `.data`, used by `Data` targets.
+    pub data: Vec<u8>,
+    /// Names of undefined symbols resolved against the symbol map while
+    /// linking this file, for the codelist map sidecar. Sorted and deduped.
+    pub referenced_symbols: Vec<String>,
 }
 
 fn process_asm(args: &Args, paths: &[PathBuf]) -> Vec<Code> {
-    // processes ~2 files per ms, bottleneck is spawning the child processes.
-    
-    // start all compilation jobs
-    let mut jobs = start_compiling(args, paths);
-    
+    // processes ~2 files per ms, bottleneck is spawning the child processes,
+    // so only files whose content cache is stale get reassembled at all.
+    let cache_keys: Vec<u32> = paths.iter().map(|p| compute_cache_key(args, p)).collect();
+    let cache = load_cache(&args.cache_dir);
+
+    let stale: Vec<usize> = (0..paths.len())
+        .filter(|&i| cache_lookup(&cache, cache_keys[i], &paths[i]).is_none())
+        .collect();
+    let stale_paths: Vec<PathBuf> = stale.iter().map(|&i| paths[i].clone()).collect();
+
+    // start compilation jobs for anything not already cached
+    let mut jobs = start_compiling(args, &stale_paths);
+
     // while we wait for them to finish, read through the headers of all asm files for the injection address
     let mut codes = collect_headers(paths);
-    
+
     // get the compiled asm from the compiled elfs and merge into codes.
-    finish_compiling(&mut codes, &mut jobs, paths);
-    
+    finish_compiling(args, &mut codes, &mut jobs, &stale_paths, &stale);
+
+    // everything else is loaded straight from the cache
+    for (i, key) in cache_keys.iter().enumerate() {
+        if let Some(entry) = cache_lookup(&cache, *key, &paths[i]) {
+            codes[i].code = entry.code.clone();
+            codes[i].data = entry.data.clone();
+            codes[i].referenced_symbols = entry.referenced_symbols.clone();
+        }
+    }
+
+    let mut new_cache = HashMap::with_capacity(paths.len());
+    for (i, key) in cache_keys.into_iter().enumerate() {
+        new_cache.insert(key, CacheEntry {
+            path: paths[i].clone(),
+            code: codes[i].code.clone(),
+            data: codes[i].data.clone(),
+            referenced_symbols: codes[i].referenced_symbols.clone(),
+        });
+    }
+    save_cache(&args.cache_dir, &new_cache);
+
     codes
 }
 
 fn collect_headers(paths: &[PathBuf]) -> Vec<Code> {
     let mut codes = Vec::with_capacity(paths.len());
     let mut err = false;
-    let mut buf = [0u8; 512];
-    
+
     'file: for asm_path in paths.iter() {
-        let mut f = match File::open(asm_path) {
-            Ok(f) => f,
+        let text = match read_to_string(asm_path) {
+            Ok(t) => t,
             Err(e) => {
-                eprintln!("{ERROR_STR} Failed to open '{}': {}", asm_path.display(), e);
-                err = true;
-                continue 'file;
-            }
-        };
-        
-        let mut read = 0;
-        let addr = 'find_addr: loop {
-            if read == buf.len() {
-                eprintln!("{ERROR_STR} File '{}' does not contain an injection address", asm_path.display());
+                eprintln!("{ERROR_STR} Failed to read '{}': {}", asm_path.display(), e);
                 err = true;
                 continue 'file;
             }
-        
-            match f.read(&mut buf[read..]) {
-                Ok(0) => {
-                    eprintln!("{ERROR_STR} File '{}' does not contain an injection address", asm_path.display());
-                    err = true;
-                    continue 'file;
-                },
-                Ok(n) => read += n,
-                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
-                Err(e) => {
-                    eprintln!("{ERROR_STR} Failed to read '{}': {}", asm_path.display(), e);
-                    err = true;
-                    continue 'file;
-                }
-            };
-            
-            'parse_addr: for w in buf[..read].windows(8) {
-                if w[0] != b'8' { continue; }
-                let mut addr = 0u32;
-                for c in w.iter().copied() {
-                    addr <<= 4;
-                    match c {
-                        b'0'..=b'9' => addr |= c as u32 - b'0' as u32,
-                        b'a'..=b'f' => addr |= c as u32 - b'a' as u32 + 10,
-                        b'A'..=b'F' => addr |= c as u32 - b'A' as u32 + 10,
-                        _ => continue 'parse_addr
-                    }
-                }
-                break 'find_addr addr;
-            }
         };
-        
+
         // ensure newline terminated
         // I hate that this is necessary
-        {
-            match f.seek(SeekFrom::End(-1)) {
-                Ok(_) => {},
-                Err(e) => {
-                    eprintln!("{ERROR_STR} Failed to seek in '{}': {e}.", asm_path.display());
-                    err = true;
-                    continue 'file;
-                }
-            }
-            let mut b = [0u8; 1];
-            match f.read_exact(&mut b) {
-                Ok(()) => {},
-                Err(e) => {
-                    eprintln!("{ERROR_STR} Failed to read '{}': {e}", asm_path.display());
+        if !text.ends_with('\n') {
+            eprintln!("{ERROR_STR} ASM file '{}' is not newline terminated. ASM files MUST be newline terminated or they may be compiled incorrectly.", asm_path.display());
+            err = true;
+            continue 'file;
+        }
+
+        let targets = match parse_header_directives(&text) {
+            Ok(targets) if !targets.is_empty() => targets,
+            Ok(_) => match find_legacy_injection_addr(&text) {
+                Some(addr) => vec![Target { addr, kind: CodeKind::C2 }],
+                None => {
+                    eprintln!("{ERROR_STR} File '{}' does not contain an injection address", asm_path.display());
                     err = true;
                     continue 'file;
                 }
-            }
-            if b[0] != b'\n' {
-                eprintln!("{ERROR_STR} ASM file '{}' is not newline terminated. ASM files MUST be newline terminated or they may be compiled incorrectly.", asm_path.display());
+            },
+            Err(msg) => {
+                eprintln!("{ERROR_STR} File '{}': {}", asm_path.display(), msg);
                 err = true;
                 continue 'file;
             }
-        }
-        
+        };
+
         codes.push(Code {
-            addr,
+            targets,
             code: Vec::new(),
+            data: Vec::new(),
+            referenced_symbols: Vec::new(),
         })
     }
-    
+
     if err { exit(1); }
-    
+
     codes
 }
 
+/// Parses leading `#`-comment lines for `@c2`/`@c0`/`@write32`/`@data` header
+/// directives, stopping at the first non-comment line. Returns an empty
+/// vec (not an error) if the file declares no directives at all, so callers
+/// can fall back to the legacy bare-address header.
+fn parse_header_directives(text: &str) -> Result<Vec<Target>, String> {
+    let mut targets = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        let Some(rest) = line.strip_prefix('#') else { break };
+        let rest = rest.trim();
+        let Some(rest) = rest.strip_prefix('@') else { continue };
+
+        let (directive, arg) = match rest.split_once(char::is_whitespace) {
+            Some((d, a)) => (d, a.trim()),
+            None => (rest, ""),
+        };
+
+        let target = match directive {
+            "c2" => Target { addr: parse_directive_addr(arg)?, kind: CodeKind::C2 },
+            "c0" if arg.is_empty() => Target { addr: 0x80000000, kind: CodeKind::C0 },
+            "c0" => return Err(format!("'@c0' does not take an argument (got '{arg}')")),
+            "write32" => Target { addr: parse_directive_addr(arg)?, kind: CodeKind::Write32 },
+            "data" => Target { addr: parse_directive_addr(arg)?, kind: CodeKind::Data },
+            other => return Err(format!("unknown header directive '@{other}'")),
+        };
+        targets.push(target);
+    }
+
+    Ok(targets)
+}
+
+fn parse_directive_addr(arg: &str) -> Result<u32, String> {
+    let s = arg.trim_start_matches("0x").trim_start_matches("0X");
+    match u32::from_str_radix(s, 16) {
+        Ok(addr) if addr & 0xF000_0000 == 0x8000_0000 => Ok(addr),
+        Ok(_) => Err(format!("address '{arg}' is not a valid 0x80xxxxxx game address")),
+        Err(_) => Err(format!("invalid address '{arg}'")),
+    }
+}
+
+/// Legacy header format: the first bare `8xxxxxxx` hex token in the first
+/// 512 bytes of the file is taken as a `@c2` injection address.
+fn find_legacy_injection_addr(text: &str) -> Option<u32> {
+    let buf = text.as_bytes();
+    let end = buf.len().min(512);
+
+    'find_addr: for w in buf[..end].windows(8) {
+        if w[0] != b'8' { continue; }
+        let mut addr = 0u32;
+        for c in w.iter().copied() {
+            addr <<= 4;
+            match c {
+                b'0'..=b'9' => addr |= c as u32 - b'0' as u32,
+                b'a'..=b'f' => addr |= c as u32 - b'a' as u32 + 10,
+                b'A'..=b'F' => addr |= c as u32 - b'A' as u32 + 10,
+                _ => continue 'find_addr,
+            }
+        }
+        return Some(addr);
+    }
+
+    None
+}
+
+// Flags passed to every `as` invocation. Shared with the cache key computation
+// so a changed arg set is correctly treated as a cache miss.
+const AS_ARGS: &[&str] = &["--warn", "-mregnames", "-mgekko", "-mbig", "-a32"];
+
 fn hash_bytes(b: &[u8]) -> u32 {
-    let mut h: u32 = 1234;
+    hash_bytes_seeded(1234, b)
+}
+
+fn hash_bytes_seeded(mut h: u32, b: &[u8]) -> u32 {
     for b in b {
         let b = *b as u32;
         h ^= b;
@@ -208,34 +376,64 @@ struct AssembleJob {
     pub out_path: PathBuf,
 }
 
+/// Hashes `path` into an 8-char lowercase-hex-ish stem, used to derive a
+/// collision-resistant-enough temp file name for it.
+fn hashed_temp_name(path: &Path) -> [u8; 8] {
+    let mut hash = hash_bytes(path.as_os_str().as_encoded_bytes());
+    let mut b = [0u8; 8];
+    for i in 0..8 {
+        let n = (hash & 0xf) as u8;
+        b[i] = b'a' + n;
+        hash >>= 4;
+    }
+    b
+}
+
+/// Writes a small wrapper source that `.include`s the prelude ahead of
+/// `path`, so the prelude's macros/constants are in scope without the
+/// original file needing to reference it itself.
+fn write_prelude_wrapper(args: &Args, prelude: &Path, path: &Path) -> std::io::Result<PathBuf> {
+    let mut wrapper_path = args.temp_path.to_path_buf();
+    wrapper_path.push(unsafe { str::from_utf8_unchecked(&hashed_temp_name(path)) });
+    wrapper_path.set_extension("s");
+
+    let contents = format!(".include \"{}\"\n.include \"{}\"\n", prelude.display(), path.display());
+    write(&wrapper_path, contents)?;
+
+    Ok(wrapper_path)
+}
+
 fn start_compiling(args: &Args, asm: &[PathBuf]) -> Vec<AssembleJob> {
     let mut jobs = Vec::with_capacity(asm.len());
     let mut err = false;
-    
+
     for path in asm {
         let mut out_path = args.temp_path.to_path_buf();
-        let mut hash = hash_bytes(path.as_os_str().as_encoded_bytes());
-        let mut b = [0u8; 8];
-        for i in 0..8 {
-            let n = (hash & 0xf) as u8;
-            b[i] = b'a' + n as u8;
-            hash >>= 4;
-        }
-        out_path.push(unsafe { str::from_utf8_unchecked(&b) });
-        
+        out_path.push(unsafe { str::from_utf8_unchecked(&hashed_temp_name(path)) });
+
+        let input_path = match &args.prelude_path {
+            Some(prelude) => match write_prelude_wrapper(args, prelude, path) {
+                Ok(p) => p,
+                Err(e) => {
+                    eprintln!("{ERROR_STR} Could not write prelude wrapper for '{}': {}", path.display(), e);
+                    err = true;
+                    continue;
+                }
+            },
+            None => path.clone(),
+        };
+
         let spawn = Command::new(&args.as_path)
-            .arg("--warn")
-            .arg("-mregnames")
-            .arg("-mgekko")
-            .arg("-mbig")
-            .arg("-a32")
+            .args(AS_ARGS)
             .arg("-I")
             .arg(path.parent().unwrap())
+            .arg("-I")
+            .arg(&args.shared_include_dir)
             .arg("-o")
             .arg(&out_path)
-            .arg(path)
+            .arg(&input_path)
             .spawn();
-        
+
         match spawn {
             Ok(child) => jobs.push(AssembleJob { child, out_path }),
             Err(e) => {
@@ -244,27 +442,219 @@ fn start_compiling(args: &Args, asm: &[PathBuf]) -> Vec<AssembleJob> {
             },
         };
     }
-    
+
     if err { exit(1); }
     jobs
 }
 
+/// Content hash used as a build-cache key for `path`: covers the source
+/// file's own bytes, the files directly inside each of its `-I` include
+/// directories (subfolders are not hashed), the prelude, and the assembler
+/// path and flags, so changing any of them is a cache miss.
+fn compute_cache_key(args: &Args, path: &Path) -> u32 {
+    let mut h = 1234;
+
+    // unreadable files fall through with no contribution from their own
+    // bytes; collect_headers/start_compiling will report the real error.
+    if let Ok(bytes) = read(path) {
+        h = hash_bytes_seeded(h, &bytes);
+    }
+
+    if let Some(include_dir) = path.parent() {
+        if let Ok(entries) = include_dir.read_dir() {
+            let mut include_files: Vec<PathBuf> = entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_file())
+                .collect();
+            include_files.sort();
+
+            for p in include_files {
+                if let Ok(bytes) = read(&p) {
+                    h = hash_bytes_seeded(h, &bytes);
+                }
+            }
+        }
+    }
+
+    if let Some(prelude) = &args.prelude_path {
+        if let Ok(bytes) = read(prelude) {
+            h = hash_bytes_seeded(h, &bytes);
+        }
+    }
+
+    if let Ok(entries) = args.shared_include_dir.read_dir() {
+        let mut shared_files: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_file())
+            .collect();
+        shared_files.sort();
+
+        for p in shared_files {
+            if let Ok(bytes) = read(&p) {
+                h = hash_bytes_seeded(h, &bytes);
+            }
+        }
+    }
+
+    h = hash_bytes_seeded(h, args.as_path.as_os_str().as_encoded_bytes());
+    for arg in AS_ARGS {
+        h = hash_bytes_seeded(h, arg.as_bytes());
+    }
+
+    h
+}
+
+/// A cached `.asm` build result: the assembled `.text`/`.data` bytes the file
+/// produced the last time its content hash was seen, plus the symbol names
+/// its relocations were resolved against (for the codelist map sidecar).
+/// `code` holds one relocated copy of `.text` per non-`Data` target, same as
+/// `Code::code`.
+struct CacheEntry {
+    /// The file this entry was built from. `hash_bytes_seeded` is a fast,
+    /// non-cryptographic hash, so a collision is possible at tree-wide scale;
+    /// checking the path alongside the hash stops a collision from silently
+    /// serving a different file's bytes into the output.
+    pub path: PathBuf,
+    pub code: Vec<Vec<u8>>,
+    pub data: Vec<u8>,
+    pub referenced_symbols: Vec<String>,
+}
+
+/// Looks up `key` in `cache`, but only returns the entry if it was built from
+/// `path` — guards against a hash collision serving another file's bytes.
+fn cache_lookup<'a>(cache: &'a HashMap<u32, CacheEntry>, key: u32, path: &Path) -> Option<&'a CacheEntry> {
+    cache.get(&key).filter(|e| e.path == path)
+}
+
+const CACHE_MANIFEST_NAME: &'static str = "manifest.bin";
+
+/// Loads the persistent build cache from `cache_dir`, if it exists. The
+/// manifest is a flat binary file of
+/// `[hash][path_len][code_chunk_count][data_len][sym_count][path][code chunks][data][sym...]`
+/// records, where each code chunk is `[chunk_len][chunk bytes]` and each
+/// `sym` is `[name_len][name bytes]`. A missing manifest is treated as an
+/// empty cache; reading stops at the first truncated or corrupt record, and
+/// the records before it are kept.
+fn load_cache(cache_dir: &Path) -> HashMap<u32, CacheEntry> {
+    let Ok(bytes) = read(cache_dir.join(CACHE_MANIFEST_NAME)) else { return HashMap::new() };
+
+    let mut cache = HashMap::new();
+    let mut rest = &bytes[..];
+    'entry: while rest.len() >= 20 {
+        let hash = u32::from_le_bytes(rest[0..4].try_into().unwrap());
+        let path_len = u32::from_le_bytes(rest[4..8].try_into().unwrap()) as usize;
+        let code_chunk_count = u32::from_le_bytes(rest[8..12].try_into().unwrap()) as usize;
+        let data_len = u32::from_le_bytes(rest[12..16].try_into().unwrap()) as usize;
+        let sym_count = u32::from_le_bytes(rest[16..20].try_into().unwrap()) as usize;
+        rest = &rest[20..];
+
+        if rest.len() < path_len { break; } // truncated/corrupt manifest
+        let Ok(path_str) = std::str::from_utf8(&rest[..path_len]) else { break };
+        let path: PathBuf = path_str.into();
+        rest = &rest[path_len..];
+
+        let mut code = Vec::with_capacity(code_chunk_count);
+        for _ in 0..code_chunk_count {
+            if rest.len() < 4 { break 'entry; } // truncated/corrupt manifest
+            let chunk_len = u32::from_le_bytes(rest[0..4].try_into().unwrap()) as usize;
+            rest = &rest[4..];
+            if rest.len() < chunk_len { break 'entry; }
+            code.push(rest[..chunk_len].to_vec());
+            rest = &rest[chunk_len..];
+        }
+
+        if rest.len() < data_len { break; } // truncated/corrupt manifest
+        let data = rest[..data_len].to_vec();
+        rest = &rest[data_len..];
+
+        let mut referenced_symbols = Vec::with_capacity(sym_count);
+        let mut sym_ok = true;
+        for _ in 0..sym_count {
+            if rest.len() < 4 { sym_ok = false; break; }
+            let name_len = u32::from_le_bytes(rest[0..4].try_into().unwrap()) as usize;
+            rest = &rest[4..];
+            if rest.len() < name_len { sym_ok = false; break; }
+            let Ok(name) = String::from_utf8(rest[..name_len].to_vec()) else { sym_ok = false; break; };
+            referenced_symbols.push(name);
+            rest = &rest[name_len..];
+        }
+        if !sym_ok { break; } // truncated/corrupt manifest
+
+        cache.insert(hash, CacheEntry { path, code, data, referenced_symbols });
+    }
+
+    cache
+}
+
+/// Writes the build cache back out, replacing the previous manifest. Entries
+/// are keyed by content hash, so files that were deleted or changed since the
+/// last run are naturally pruned rather than carried forward.
+fn save_cache(cache_dir: &Path, cache: &HashMap<u32, CacheEntry>) {
+    if let Err(e) = create_dir_all(cache_dir) {
+        eprintln!("{WARNING_STR} Could not create build cache dir '{}': {}", cache_dir.display(), e);
+        return;
+    }
+
+    let mut bytes = Vec::new();
+    for (hash, entry) in cache {
+        let path_bytes = entry.path.to_string_lossy().into_owned().into_bytes();
+
+        bytes.extend_from_slice(&hash.to_le_bytes());
+        bytes.extend_from_slice(&(path_bytes.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(entry.code.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&(entry.referenced_symbols.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&path_bytes);
+        for chunk in &entry.code {
+            bytes.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(chunk);
+        }
+        bytes.extend_from_slice(&entry.data);
+        for name in &entry.referenced_symbols {
+            bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(name.as_bytes());
+        }
+    }
+
+    if let Err(e) = write(cache_dir.join(CACHE_MANIFEST_NAME), &bytes) {
+        eprintln!("{WARNING_STR} Could not write build cache manifest in '{}': {}", cache_dir.display(), e);
+    }
+}
+
+/// Finds the section named `name` in `elf`, returning its index alongside
+/// its header. `ElfStream::section_header_by_name` only hands back the
+/// header itself, but resolving a symbol's `st_shndx` needs the index, so
+/// this cross-references the header against `section_headers()` (`&self`,
+/// so it doesn't conflict with the `&mut self` lookup above it).
+fn section_index_by_name<E: elf::endian::EndianParse, S: std::io::Read + std::io::Seek>(
+    elf: &mut elf::ElfStream<E, S>,
+    name: &str,
+) -> Result<Option<(usize, elf::section::SectionHeader)>, elf::ParseError> {
+    let Some(header) = elf.section_header_by_name(name)?.copied() else { return Ok(None) };
+    let idx = elf.section_headers().iter().position(|h| *h == header)
+        .expect("section_header_by_name returned a header absent from section_headers()");
+    Ok(Some((idx, header)))
+}
+
 fn finish_compiling(
+    args: &Args,
     codes: &mut [Code],
     jobs: &mut [AssembleJob],
-    paths: &[PathBuf],
+    stale_paths: &[PathBuf],
+    stale: &[usize],
 ) {
-    let mut undef = Vec::new();
     let mut err = false;
-    'file: for i in 0..codes.len() {
-        if !jobs[i].child.wait().unwrap().success() {
+    'file: for j in 0..jobs.len() {
+        if !jobs[j].child.wait().unwrap().success() {
             err = true;
             continue;
         }
-        let code = &mut codes[i];
-        let path = &paths[i];
-        
-        let mut elf_file = match File::open(&jobs[i].out_path) {
+        let code = &mut codes[stale[j]];
+        let path = &stale_paths[j];
+
+        let mut elf_file = match File::open(&jobs[j].out_path) {
             Ok(f) => f,
             Err(e) => {
                 eprintln!("{ERROR_STR} Failed to open compiled elf for '{}': {}", path.display(), e);
@@ -280,12 +670,17 @@ fn finish_compiling(
                 continue 'file;
             }
         };
-        
-        // check for undefined symbols
-        let (symbol_table, string_table) = match elf.symbol_table() {
-            Ok(Some(s)) => s,
+
+        // Every other section has to be read out of `elf` before calling
+        // `symbol_table` below: the tables it returns borrow `elf` for the
+        // rest of this iteration, so no further `&mut elf` call is possible
+        // once we have them.
+
+        // Extract code
+        let (text_idx, text_header) = match section_index_by_name(&mut elf, ".text") {
+            Ok(Some(r)) => r,
             Ok(None) => {
-                eprintln!("{ERROR_STR} Failed to extract string table and symbol table sections in compiled elf for '{}'", path.display());
+                eprintln!("{ERROR_STR} Failed to extract .text section in compiled elf for '{}'", path.display());
                 err = true;
                 continue 'file;
             }
@@ -295,45 +690,86 @@ fn finish_compiling(
                 continue 'file;
             }
         };
-        undef.clear();
-        let mut symbol_iter = symbol_table.iter();
-        symbol_iter.next(); // skip null entry
-        for s in symbol_iter {
-            if s.is_undefined() {
-                undef.push(s);
-            }
-        }
-        if !undef.is_empty() {
-            undef.sort_by_key(|u| u.st_name);
-            undef.dedup();
-            for u in undef.iter() {
-                let name = match string_table.get(u.st_name as usize) {
-                    Ok("") | Err(_) => "(unnamed symbol)",
-                    Ok(name) => name,
-                };
-                eprintln!("{WARNING_STR} Undefined symbol: {name}");
+        let text = match elf.section_data(&text_header) {
+            Ok((b, None)) => b.to_vec(),
+            Ok((_, Some(_))) => {
+                eprintln!("{ERROR_STR} Cannot parse compressed sections for '{}'", path.display());
+                err = true;
+                continue 'file;
             }
-            eprintln!("{WARNING_STR} {} undefined symbols in '{}'", undef.len(), path.display());
-        }
-        
-        // Extract code
-        let text_header = match elf.section_header_by_name(".text") {
-            Ok(Some(f)) => *f,
-            Ok(None) => {
-                eprintln!("{ERROR_STR} Failed to extract .text section in compiled elf for '{}'", path.display());
+            Err(e) => {
+                eprintln!("{ERROR_STR} Failed to parse compiled elf for '{}': {}", path.display(), e);
                 err = true;
                 continue 'file;
             }
+        };
+        if text.is_empty() {
+            eprintln!("{WARNING_STR} File '{}' has no ASM! Skipping...", path.display());
+        }
+
+        // Relocations, read out and owned up-front for the same reason.
+        // `.copied()` out of the header right away so the borrow from
+        // `section_header_by_name` doesn't overlap the `&mut elf` call below.
+        let rela_header = match elf.section_header_by_name(".rela.text") {
+            Ok(opt) => opt.copied(),
             Err(e) => {
                 eprintln!("{ERROR_STR} Failed to parse compiled elf for '{}': {}", path.display(), e);
                 err = true;
                 continue 'file;
             }
         };
-        let text = match elf.section_data(&text_header) {
-            Ok((b, None)) => b,
-            Ok((_, Some(_))) => {
-                eprintln!("{ERROR_STR} Cannot parse compressed sections for '{}'", path.display());
+        let relas: Vec<elf::relocation::Rela> = match rela_header {
+            Some(h) => match elf.section_data_as_relas(&h) {
+                Ok(r) => r.collect(),
+                Err(e) => {
+                    eprintln!("{ERROR_STR} Failed to parse relocations for '{}': {}", path.display(), e);
+                    err = true;
+                    continue 'file;
+                }
+            },
+            None => Vec::new(), // no relocations to resolve
+        };
+
+        // `@data` targets emit the file's `.data` section instead of `.text`.
+        let data_idx = if code.targets.iter().any(|t| t.kind == CodeKind::Data) {
+            let (data_idx, data_header) = match section_index_by_name(&mut elf, ".data") {
+                Ok(Some(r)) => r,
+                Ok(None) => {
+                    eprintln!("{ERROR_STR} File '{}' has an '@data' target but assembles no .data section", path.display());
+                    err = true;
+                    continue 'file;
+                }
+                Err(e) => {
+                    eprintln!("{ERROR_STR} Failed to parse compiled elf for '{}': {}", path.display(), e);
+                    err = true;
+                    continue 'file;
+                }
+            };
+            let data = match elf.section_data(&data_header) {
+                Ok((b, None)) => b.to_vec(),
+                Ok((_, Some(_))) => {
+                    eprintln!("{ERROR_STR} Cannot parse compressed sections for '{}'", path.display());
+                    err = true;
+                    continue 'file;
+                }
+                Err(e) => {
+                    eprintln!("{ERROR_STR} Failed to parse compiled elf for '{}': {}", path.display(), e);
+                    err = true;
+                    continue 'file;
+                }
+            };
+            code.data = data;
+            Some(data_idx)
+        } else {
+            None
+        };
+
+        // This is the last `&mut elf` call for this file: the tables it
+        // returns borrow `elf`, so everything else has to be extracted above.
+        let (symbol_table, string_table) = match elf.symbol_table() {
+            Ok(Some(s)) => s,
+            Ok(None) => {
+                eprintln!("{ERROR_STR} Failed to extract string table and symbol table sections in compiled elf for '{}'", path.display());
                 err = true;
                 continue 'file;
             }
@@ -343,54 +779,226 @@ fn finish_compiling(
                 continue 'file;
             }
         };
-        if text.is_empty() {
-            eprintln!("{WARNING_STR} File '{}' has no ASM! Skipping...", path.display());
+
+        // Link any `bl`/load-address references against their symbol: symbols
+        // defined within this file resolve to the placed address of whichever
+        // of `.text`/`.data` they're defined in plus their offset into it,
+        // symbols left undefined
+        // must be present in the symbol map passed on the CLI. A fresh copy
+        // of `.text` is relocated per non-`@data` target, since branches and
+        // absolute loads bake in the placement address and the same payload
+        // can be installed at several different sites.
+        let data_addr = code.targets.iter().find(|t| t.kind == CodeKind::Data).map(|t| t.addr);
+
+        code.code.clear();
+        for target in code.targets.iter().filter(|t| t.kind != CodeKind::Data) {
+            let mut relocated = text.clone();
+            if apply_relocations(
+                target.addr, data_addr, &mut relocated, &mut code.referenced_symbols,
+                &relas, text_idx, data_idx, target.kind == CodeKind::C0, path,
+                &symbol_table, &string_table, &args.symbol_map,
+            ).is_err() {
+                err = true;
+                continue 'file;
+            }
+            code.code.push(relocated);
         }
-        
-        code.code = text.to_vec();
+        code.referenced_symbols.sort();
+        code.referenced_symbols.dedup();
     }
-    
+
     if err { exit(1); }
 }
 
-fn write_codes(args: &Args, codes: &[Code]) {
-    let max_len = codes.iter().map(|c| c.code.len()).sum::<usize>() * 2;
+/// Patches `code` (a copy of the file's `.text`) in place according to
+/// `relas` (its `.rela.text` entries), resolving each referenced symbol
+/// either to `text_addr`/`data_addr` plus its offset (if locally defined
+/// within `.text` or `.data`, per `text_idx`/`data_idx`) or via `symbol_map`
+/// (if left undefined).
+/// `text_addr` is the runtime address this particular copy of `.text` is
+/// placed at — callers relocate a fresh copy per target, since each site a
+/// payload installs at needs its own absolute/PC-relative patches; `data_addr`
+/// is the file's `@data` target address, if it has one. Names resolved via
+/// `symbol_map` are appended to `referenced`, for the codelist map sidecar.
+/// `is_c0` marks a `@c0` target: its `text_addr` is a placeholder (C0 code
+/// runs once during init at a scratch address unknown at build time), so a
+/// PC-relative branch to a fixed symbol-map address can't be computed and is
+/// rejected instead of silently baking in a displacement against the wrong base.
+fn apply_relocations(
+    text_addr: u32,
+    data_addr: Option<u32>,
+    code: &mut Vec<u8>,
+    referenced: &mut Vec<String>,
+    relas: &[elf::relocation::Rela],
+    text_idx: usize,
+    data_idx: Option<usize>,
+    is_c0: bool,
+    path: &Path,
+    symbol_table: &elf::symbol::SymbolTable<elf::endian::BigEndian>,
+    string_table: &elf::string_table::StringTable,
+    symbol_map: &SymbolMap,
+) -> Result<(), ()> {
+    for rela in relas {
+        let sym = match symbol_table.get(rela.r_sym as usize) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("{ERROR_STR} Failed to resolve relocation symbol in '{}': {}", path.display(), e);
+                return Err(());
+            }
+        };
+
+        let (s, from_symbol_map) = if sym.is_undefined() {
+            let name = match string_table.get(sym.st_name as usize) {
+                Ok("") | Err(_) => "(unnamed symbol)",
+                Ok(name) => name,
+            };
+            match symbol_map.get(name) {
+                Some(addr) => {
+                    referenced.push(name.to_string());
+                    (*addr, true)
+                }
+                None => {
+                    eprintln!("{ERROR_STR} Undefined symbol '{}' in '{}' is not present in the symbol map", name, path.display());
+                    return Err(());
+                }
+            }
+        } else if sym.st_shndx as usize == text_idx {
+            (text_addr.wrapping_add(sym.st_value as u32), false)
+        } else if Some(sym.st_shndx as usize) == data_idx {
+            // data_idx is only Some when data_addr is: both come from the
+            // same `@data` target check in finish_compiling.
+            (data_addr.unwrap().wrapping_add(sym.st_value as u32), false)
+        } else {
+            eprintln!("{ERROR_STR} Locally-defined symbol in '{}' is not in .text or in the .data of an '@data' target, cannot resolve its placed address", path.display());
+            return Err(());
+        };
+
+        let site = rela.r_offset as usize;
+        let Some(instr_bytes) = code.get(site..site + 4) else {
+            eprintln!("{ERROR_STR} Relocation in '{}' targets offset {:#x} outside of .text", path.display(), site);
+            return Err(());
+        };
+        let instr = u32::from_be_bytes(instr_bytes.try_into().unwrap());
+        let target = s as i64 + rela.r_addend;
+        let p = text_addr as i64 + rela.r_offset as i64;
+
+        let patched = match rela.r_type {
+            R_PPC_ADDR32 => target as u32,
+            R_PPC_ADDR16_LO => (instr & 0xFFFF0000) | (target as u32 & 0xFFFF),
+            R_PPC_ADDR16_HI => (instr & 0xFFFF0000) | ((target as u32 >> 16) & 0xFFFF),
+            R_PPC_ADDR16_HA => (instr & 0xFFFF0000) | (((target as u32 >> 16).wrapping_add((target as u32 >> 15) & 1)) & 0xFFFF),
+            R_PPC_REL24 => {
+                if is_c0 && from_symbol_map {
+                    eprintln!("{ERROR_STR} '@c0' target in '{}' has a PC-relative branch to a symbol-map address at offset {:#x}, but C0 code has no fixed placement address to compute the displacement from", path.display(), site);
+                    return Err(());
+                }
+                let delta = target - p;
+                if delta < -(1 << 25) || delta >= (1 << 25) {
+                    eprintln!("{ERROR_STR} Branch relocation in '{}' at offset {:#x} is out of range (±32 MiB)", path.display(), site);
+                    return Err(());
+                }
+                (instr & !0x03FFFFFC) | (delta as u32 & 0x03FFFFFC)
+            }
+            R_PPC_REL14 => {
+                if is_c0 && from_symbol_map {
+                    eprintln!("{ERROR_STR} '@c0' target in '{}' has a PC-relative branch to a symbol-map address at offset {:#x}, but C0 code has no fixed placement address to compute the displacement from", path.display(), site);
+                    return Err(());
+                }
+                let delta = target - p;
+                if delta < -(1 << 15) || delta >= (1 << 15) {
+                    eprintln!("{ERROR_STR} Branch relocation in '{}' at offset {:#x} is out of range (±32 KiB)", path.display(), site);
+                    return Err(());
+                }
+                (instr & !0x0000FFFC) | (delta as u32 & 0x0000FFFC)
+            }
+            R_PPC_ADDR14 => {
+                if target < -(1 << 15) || target >= (1 << 15) {
+                    eprintln!("{ERROR_STR} Branch relocation in '{}' at offset {:#x} is out of range (±32 KiB)", path.display(), site);
+                    return Err(());
+                }
+                (instr & !0x0000FFFC) | (target as u32 & 0x0000FFFC)
+            }
+            other => {
+                eprintln!("{ERROR_STR} Unsupported relocation type {} for '{}'", other, path.display());
+                return Err(());
+            }
+        };
+
+        code[site..site + 4].copy_from_slice(&patched.to_be_bytes());
+    }
+
+    Ok(())
+}
+
+/// A single resolved Gecko code entry: one `Target` plus the bytes it emits,
+/// and the source-file metadata needed for the codelist map sidecar.
+struct Emission {
+    pub addr: u32,
+    pub kind: CodeKind,
+    pub bytes: Vec<u8>,
+    pub source: PathBuf,
+    pub referenced_symbols: Vec<String>,
+}
+
+fn write_codes(args: &Args, emissions: &[Emission]) {
+    let max_len = emissions.iter().map(|e| e.bytes.len()).sum::<usize>() * 2;
     let mut data = Vec::with_capacity(max_len);
-    
+
     data.extend_from_slice(&[0x00, 0xD0, 0xC0, 0xDE, 0x00, 0xD0, 0xC0, 0xDE]);
-    
-    for c in codes {
-        if c.code.is_empty() { continue; }
-    
-        assert!(c.code.len() % 4 == 0);
-        let mut addr = (c.addr - 0x80000000).to_be_bytes();
-        if c.code.len() == 4 {
-            addr[0] |= 0x04;
-            data.extend_from_slice(&addr);
-            data.extend_from_slice(c.code.as_slice());
-        } else {
-            addr[0] |= 0xC2;
-            data.extend_from_slice(&addr);
-            
-            let code_words = c.code.len() as u32 / 4;
-            let code_lines = if code_words & 1 == 0 {
-                (code_words + 2) / 2
-            } else {
-                (code_words + 1) / 2
-            };
-            data.extend_from_slice(&code_lines.to_be_bytes());
-            
-            data.extend_from_slice(c.code.as_slice());
-            
-            if code_words & 1 == 0 {
-                data.extend_from_slice(&[0x60, 0x00, 0x00, 0x00]);
+
+    for e in emissions {
+        if e.bytes.is_empty() { continue; }
+
+        match e.kind {
+            CodeKind::Write32 => {
+                if e.bytes.len() != 4 {
+                    eprintln!("{ERROR_STR} '@write32' target at {:#x} must assemble to exactly one word (got {} bytes)", e.addr, e.bytes.len());
+                    exit(1);
+                }
+                let mut addr = (e.addr - 0x80000000).to_be_bytes();
+                addr[0] |= 0x04;
+                data.extend_from_slice(&addr);
+                data.extend_from_slice(e.bytes.as_slice());
+            }
+            CodeKind::C2 | CodeKind::C0 => {
+                if e.bytes.len() % 4 != 0 {
+                    eprintln!("{ERROR_STR} '@{}' target at {:#x} assembled to a non-word-aligned size ({} bytes)", if e.kind == CodeKind::C2 { "c2" } else { "c0" }, e.addr, e.bytes.len());
+                    exit(1);
+                }
+                let mut addr = (e.addr - 0x80000000).to_be_bytes();
+                addr[0] |= if e.kind == CodeKind::C2 { 0xC2 } else { 0xC0 };
+                data.extend_from_slice(&addr);
+
+                let code_words = e.bytes.len() as u32 / 4;
+                let code_lines = if code_words & 1 == 0 {
+                    (code_words + 2) / 2
+                } else {
+                    (code_words + 1) / 2
+                };
+                data.extend_from_slice(&code_lines.to_be_bytes());
+
+                data.extend_from_slice(e.bytes.as_slice());
+
+                if code_words & 1 == 0 {
+                    data.extend_from_slice(&[0x60, 0x00, 0x00, 0x00]);
+                }
+                data.extend_from_slice(&[0x00; 4]);
+            }
+            CodeKind::Data => {
+                let mut addr = (e.addr - 0x80000000).to_be_bytes();
+                addr[0] |= 0x06;
+                data.extend_from_slice(&addr);
+                data.extend_from_slice(&(e.bytes.len() as u32).to_be_bytes());
+                data.extend_from_slice(e.bytes.as_slice());
+
+                let pad = (4 - (e.bytes.len() % 4)) % 4;
+                data.extend(std::iter::repeat(0u8).take(pad));
             }
-            data.extend_from_slice(&[0x00; 4]);
         }
     }
 
     data.extend_from_slice(&[0xF0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
-    
+
     match write(&args.out_path, &data) {
         Ok(f) => f,
         Err(e) => {
@@ -400,13 +1008,87 @@ fn write_codes(args: &Args, codes: &[Code]) {
     };
 }
 
+/// `out_path` with its extension replaced, e.g. `codes.gct` -> `codes.map`.
+fn codelist_map_path(out_path: &Path) -> PathBuf {
+    out_path.with_extension("map")
+}
+
+/// Writes a Dolphin-loadable `.map` sidecar next to `out_path`, one
+/// `.text section layout` entry per emitted code with a placement address
+/// (`C0` codes are listed in a separate section), so a crash at an injection
+/// address can be traced back to the originating `.asm` file. This is purely
+/// a debugging aid; a failure to write it is a warning, not a build error.
+fn write_codelist_map(args: &Args, emissions: &[Emission]) {
+    let mut text = String::new();
+    text.push_str(".text section layout\n");
+
+    for e in emissions {
+        // `C0` codes run once during init and aren't placed at `e.addr`, so
+        // they have no real address range to report here; they get their
+        // own section below instead.
+        if e.bytes.is_empty() || e.kind == CodeKind::C0 { continue; }
+
+        let label = e.source.strip_prefix(&args.asm_path).unwrap_or(&e.source).display();
+        text.push_str(&format!(
+            "  {:08x} {:06x} 00 {:08x}  4  {:?} {}\n",
+            e.addr, e.bytes.len(), e.addr, e.kind, label,
+        ));
+
+        if !e.referenced_symbols.is_empty() {
+            text.push_str(&format!("    ; references: {}\n", e.referenced_symbols.join(", ")));
+        }
+    }
+
+    let once: Vec<&Emission> = emissions.iter().filter(|e| e.kind == CodeKind::C0 && !e.bytes.is_empty()).collect();
+    if !once.is_empty() {
+        text.push_str("\nonce (run during init, no placement address)\n");
+        for e in once {
+            let label = e.source.strip_prefix(&args.asm_path).unwrap_or(&e.source).display();
+            text.push_str(&format!("  {:06x}  {:?} {}\n", e.bytes.len(), e.kind, label));
+
+            if !e.referenced_symbols.is_empty() {
+                text.push_str(&format!("    ; references: {}\n", e.referenced_symbols.join(", ")));
+            }
+        }
+    }
+
+    let path = codelist_map_path(&args.out_path);
+    if let Err(e) = write(&path, text) {
+        eprintln!("{WARNING_STR} Could not write codelist map '{}': {}", path.display(), e);
+    }
+}
+
 fn main() {
     let t = Instant::now();
     let args = parse_args();
     let mut asm_paths = Vec::new();
     collect_asm(&mut asm_paths, &args.asm_path);
-    let mut codes = process_asm(&args, &asm_paths);
-    codes.sort_by_key(|c| c.addr);
-    write_codes(&args, &codes);
+    let codes = process_asm(&args, &asm_paths);
+
+    let mut emissions = Vec::new();
+    for (code, source) in codes.iter().zip(asm_paths.iter()) {
+        // `code.code` holds one relocated copy per non-`Data` target, in the
+        // same order as `targets` filtered the same way.
+        let mut non_data_targets = code.code.iter();
+        for target in &code.targets {
+            let bytes = match target.kind {
+                CodeKind::Data => code.data.clone(),
+                CodeKind::C2 | CodeKind::C0 | CodeKind::Write32 => {
+                    non_data_targets.next().cloned().unwrap_or_default()
+                }
+            };
+            emissions.push(Emission {
+                addr: target.addr,
+                kind: target.kind,
+                bytes,
+                source: source.clone(),
+                referenced_symbols: code.referenced_symbols.clone(),
+            });
+        }
+    }
+    emissions.sort_by_key(|e| e.addr);
+
+    write_codes(&args, &emissions);
+    write_codelist_map(&args, &emissions);
     println!("processed {} files in {}ms", asm_paths.len(), t.elapsed().as_millis());
 }